@@ -1,5 +1,8 @@
+use crate::client::middleware::CosmMiddleware;
+use crate::client::nonce::AccountSequence;
 use crate::config::cfg::ChainCfg;
 use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
 use cosmos_sdk_proto::cosmos::auth::v1beta1::{
     BaseAccount, QueryAccountRequest, QueryAccountResponse,
 };
@@ -7,23 +10,20 @@ use cosmos_sdk_proto::cosmos::tx::v1beta1::{SimulateRequest, SimulateResponse};
 use cosmos_sdk_proto::cosmwasm::wasm::v1::{
     QuerySmartContractStateRequest, QuerySmartContractStateResponse,
 };
-use cosmrs::cosmwasm::{MsgExecuteContract, MsgInstantiateContract};
 use cosmrs::rpc::endpoint::broadcast::tx_commit::{Response, TxResult};
 use cosmrs::rpc::Client;
-use cosmrs::tendermint::abci::tag::Key;
-use cosmrs::tendermint::abci::{Code, Event};
-use cosmrs::tx::{Fee, Msg, SignDoc, SignerInfo};
-use cosmrs::{
-    cosmwasm::MsgStoreCode,
-    crypto::secp256k1::SigningKey,
-    rpc::HttpClient,
-    tx::{self},
-};
-use cosmrs::{AccountId, Any, Coin};
+use cosmrs::tendermint::abci::Code;
+use cosmrs::tendermint::Hash;
+use cosmrs::tx::{Fee, SignDoc, SignerInfo};
+use cosmrs::{crypto::secp256k1::SigningKey, rpc::HttpClient, tx};
+use cosmrs::{AccountId, Coin};
 use prost::Message;
 use std::future::Future;
-use std::str::FromStr;
 
+/// The base layer of a `CosmClient` stack: talks to the chain directly over
+/// RPC, pricing gas from the static `cfg.gas_prices`, with no nonce-caching
+/// or retry behavior of its own. Wrap it in the `middleware` module's layers
+/// to add those or to replace the gas pricing.
 pub struct CosmClient {
     client: HttpClient,
     cfg: ChainCfg,
@@ -36,110 +36,21 @@ impl CosmClient {
             cfg,
         })
     }
+}
 
-    pub async fn store(
-        &self,
-        payload: Vec<u8>,
-        signing_key: &SigningKey,
-    ) -> Result<StoreCodeResponse> {
-        let signing_public_key = signing_key.public_key();
-        let sender_account_id = signing_public_key.account_id(&self.cfg.prefix).unwrap();
-
-        let msg = MsgStoreCode {
-            sender: sender_account_id.clone(),
-            wasm_byte_code: payload,
-            instantiate_permission: None,
-        }
-        .to_any()
-        .unwrap();
-
-        let tx_res = self.send_tx(msg, signing_key, sender_account_id).await?;
-
-        let res = self
-            .find_event(&tx_res, "store_code")
-            .context("error storing code")?;
-
-        let code_id = res
-            .attributes
-            .iter()
-            .find(|a| a.key == Key::from_str("code_id").unwrap())
-            .unwrap()
-            .value
-            .as_ref()
-            .parse::<u64>()?;
-
-        Ok(StoreCodeResponse {
-            code_id,
-            data: tx_res.deliver_tx,
-        })
-    }
-
-    pub async fn instantiate(
-        &self,
-        code_id: u64,
-        payload: Vec<u8>,
-        signing_key: &SigningKey,
-    ) -> Result<InstantiateResponse> {
-        let signing_public_key = signing_key.public_key();
-        let sender_account_id = signing_public_key.account_id(&self.cfg.prefix).unwrap();
-
-        let msg = MsgInstantiateContract {
-            sender: sender_account_id.clone(),
-            admin: None, // TODO
-            code_id,
-            label: Some("cosm-orc".to_string()),
-            msg: payload,
-            funds: vec![], // TODO
-        }
-        .to_any()
-        .unwrap();
-
-        let tx_res = self.send_tx(msg, signing_key, sender_account_id).await?;
+#[async_trait]
+impl CosmMiddleware for CosmClient {
+    type Inner = CosmClient;
 
-        let res = self
-            .find_event(&tx_res, "instantiate")
-            .context("error instantiating code")?;
-
-        let addr = res
-            .attributes
-            .iter()
-            .find(|a| a.key == Key::from_str("_contract_address").unwrap())
-            .unwrap()
-            .value
-            .to_string();
-
-        Ok(InstantiateResponse {
-            address: addr,
-            data: tx_res.deliver_tx,
-        })
+    fn inner(&self) -> &CosmClient {
+        self
     }
 
-    pub async fn execute(
-        &self,
-        address: String,
-        payload: Vec<u8>,
-        signing_key: &SigningKey,
-    ) -> Result<ExecResponse> {
-        let signing_public_key = signing_key.public_key();
-        let sender_account_id = signing_public_key.account_id(&self.cfg.prefix).unwrap();
-
-        let msg = MsgExecuteContract {
-            sender: sender_account_id.clone(),
-            contract: address.parse().unwrap(),
-            msg: payload,
-            funds: vec![], // TODO
-        }
-        .to_any()
-        .unwrap();
-
-        let tx_res = self.send_tx(msg, signing_key, sender_account_id).await?;
-
-        Ok(ExecResponse {
-            data: tx_res.deliver_tx,
-        })
+    fn cfg(&self) -> &ChainCfg {
+        &self.cfg
     }
 
-    pub async fn query(&self, address: String, payload: Vec<u8>) -> Result<QueryResponse> {
+    async fn query(&self, address: String, payload: Vec<u8>) -> Result<QueryResponse> {
         let req = QuerySmartContractStateRequest {
             address: address.parse().unwrap(),
             query_data: payload,
@@ -171,38 +82,7 @@ impl CosmClient {
         })
     }
 
-    async fn send_tx(&self, msg: Any, key: &SigningKey, account_id: AccountId) -> Result<Response> {
-        let timeout_height = 0u16; // TODO
-        let account = self.account(account_id).await?;
-
-        let tx_body = tx::Body::new(vec![msg], "MEMO", timeout_height);
-
-        let fee = self.simulate_gas_fee(&tx_body, &account, key).await?;
-
-        let auth_info =
-            SignerInfo::single_direct(Some(key.public_key()), account.sequence).auth_info(fee);
-        let sign_doc = SignDoc::new(
-            &tx_body,
-            &auth_info,
-            &self.cfg.chain_id.parse()?,
-            account.account_number,
-        )
-        .unwrap();
-        let tx_raw = sign_doc.sign(key).unwrap();
-
-        let tx_commit_response = tx_raw.broadcast_commit(&self.client).await.unwrap();
-
-        if tx_commit_response.check_tx.code.is_err() {
-            bail!("check_tx failed: {:?}", tx_commit_response.check_tx)
-        }
-        if tx_commit_response.deliver_tx.code.is_err() {
-            bail!("deliver_tx failed: {:?}", tx_commit_response.deliver_tx);
-        }
-
-        Ok(tx_commit_response)
-    }
-
-    async fn account(&self, account_id: AccountId) -> Result<BaseAccount> {
+    async fn account(&self, account_id: AccountId) -> Result<AccountSequence> {
         let req = QueryAccountRequest {
             address: account_id.as_ref().into(),
         };
@@ -224,14 +104,19 @@ impl CosmClient {
             .account
             .context("cannot fetch account")?;
 
-        Ok(BaseAccount::decode(res.value.as_slice())?)
+        let account = BaseAccount::decode(res.value.as_slice())?;
+
+        Ok(AccountSequence {
+            account_number: account.account_number,
+            sequence: account.sequence,
+        })
     }
 
     #[allow(deprecated)]
     async fn simulate_gas_fee(
         &self,
         tx: &tx::Body,
-        account: &BaseAccount,
+        account: AccountSequence,
         key: &SigningKey,
     ) -> Result<Fee> {
         // TODO: support passing in the exact fee too (should be on a per process_msg() call)
@@ -285,16 +170,53 @@ impl CosmClient {
         Ok(Fee::from_amount_and_gas(amount, gas_limit as u64))
     }
 
-    fn find_event(&self, res: &Response, key_name: &str) -> Option<Event> {
-        for event in &res.deliver_tx.events {
-            if event.type_str == key_name {
-                return Some(event.clone());
-            }
+    /// Broadcasts a signed tx and waits for it to be committed to a block.
+    /// Only transport-level failures are surfaced here; `send_tx` is the one
+    /// that turns a failing `check_tx`/`deliver_tx` code into an error, so
+    /// middleware layers can inspect the result first (e.g. to retry on a
+    /// sequence mismatch).
+    async fn broadcast_commit(&self, tx_bytes: Vec<u8>) -> Result<Response> {
+        Ok(self.client.broadcast_tx_commit(tx_bytes).await?)
+    }
+
+    async fn broadcast_sync(&self, tx_bytes: Vec<u8>) -> Result<Hash> {
+        let res = self.client.broadcast_tx_sync(tx_bytes).await?;
+        if res.code.is_err() {
+            bail!("broadcast_tx_sync failed: {:?}", res);
+        }
+        Ok(res.hash)
+    }
+
+    async fn broadcast_async(&self, tx_bytes: Vec<u8>) -> Result<Hash> {
+        let res = self.client.broadcast_tx_async(tx_bytes).await?;
+        Ok(res.hash)
+    }
+
+    async fn tx_by_hash(&self, hash: Hash) -> Result<Option<Response>> {
+        match self.client.tx(hash, false).await {
+            Ok(res) => Ok(Some(Response {
+                check_tx: TxResult {
+                    code: Code::Ok,
+                    ..Default::default()
+                },
+                deliver_tx: res.tx_result,
+                hash: res.hash,
+                height: res.height,
+            })),
+            Err(err) if is_tx_not_found(&err) => Ok(None),
+            Err(err) => Err(err.into()),
         }
-        None
     }
 }
 
+/// The tendermint RPC `/tx` endpoint has no distinct "not found" error code
+/// — it returns a generic internal error whose message says so — so that's
+/// what `poll_for_tx` needs to check to tell "still pending" apart from a
+/// real transport/server failure worth surfacing.
+fn is_tx_not_found(err: &cosmrs::rpc::Error) -> bool {
+    err.to_string().contains("not found")
+}
+
 pub fn tokio_block<F: Future>(f: F) -> F::Output {
     tokio::runtime::Builder::new_current_thread()
         .enable_all()
@@ -320,6 +242,32 @@ pub struct ExecResponse {
     pub data: TxResult,
 }
 
+#[derive(Debug)]
+pub struct ExecBatchResponse {
+    pub data: TxResult,
+}
+
+#[derive(Debug)]
+pub struct SendResponse {
+    pub data: TxResult,
+}
+
+#[derive(Debug)]
+pub struct MigrateResponse {
+    pub code_id: u64,
+    pub data: TxResult,
+}
+
+#[derive(Debug)]
+pub struct UpdateAdminResponse {
+    pub data: TxResult,
+}
+
+#[derive(Debug)]
+pub struct ClearAdminResponse {
+    pub data: TxResult,
+}
+
 #[derive(Debug)]
 pub struct QueryResponse {
     pub data: TxResult,
@@ -0,0 +1,147 @@
+use anyhow::{Context, Result};
+use bip39::Mnemonic;
+use cosmrs::bip32::DerivationPath;
+use cosmrs::crypto::secp256k1::SigningKey;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+/// Cosmos SDK's default HD path: `m/44'/118'/0'/0/0`.
+pub const COSMOS_BASE_DERIVATION_PATH: &str = "m/44'/118'/0'/0/0";
+
+/// An in-memory keybase that restores `SigningKey`s from BIP39 mnemonics and
+/// looks them up by a caller-chosen alias, so methods on `CosmClient` can be
+/// invoked with a key name instead of a raw `SigningKey`.
+#[derive(Default)]
+pub struct Keyring {
+    keys: HashMap<String, SigningKey>,
+}
+
+impl Keyring {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Restores a `SigningKey` from `mnemonic` along `derivation_path` and
+    /// stores it under `name`, overwriting any existing key with that name.
+    pub fn add_key_from_mnemonic(
+        &mut self,
+        name: &str,
+        mnemonic: &str,
+        derivation_path: &str,
+    ) -> Result<()> {
+        let mnemonic = Mnemonic::parse_normalized(mnemonic).context("invalid mnemonic phrase")?;
+        let seed = mnemonic.to_seed("");
+
+        let path = DerivationPath::from_str(derivation_path).context("invalid derivation path")?;
+        let signing_key =
+            SigningKey::derive_from_path(seed, &path).context("error deriving signing key")?;
+
+        self.keys.insert(name.to_string(), signing_key);
+        Ok(())
+    }
+
+    /// Restores a `SigningKey` from `mnemonic` using the default Cosmos HD
+    /// path with a configurable `coin_type` / `account_index`.
+    pub fn add_key_from_mnemonic_with_options(
+        &mut self,
+        name: &str,
+        mnemonic: &str,
+        coin_type: u32,
+        account_index: u32,
+    ) -> Result<()> {
+        let derivation_path = format!("m/44'/{coin_type}'/0'/0/{account_index}");
+        self.add_key_from_mnemonic(name, mnemonic, &derivation_path)
+    }
+
+    /// Stores an already-derived `SigningKey` under `name`.
+    pub fn add_key(&mut self, name: &str, signing_key: SigningKey) {
+        self.keys.insert(name.to_string(), signing_key);
+    }
+
+    /// Looks up a previously added key by its alias.
+    pub fn sign_key(&self, name: &str) -> Result<&SigningKey> {
+        self.keys
+            .get(name)
+            .with_context(|| format!("no key named '{name}' in keyring"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MNEMONIC_A: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+    const MNEMONIC_B: &str =
+        "legal winner thank year wave sausage worth useful legal winner thank yellow";
+
+    #[test]
+    fn add_key_from_mnemonic_then_sign_key_roundtrip() {
+        let mut keyring = Keyring::new();
+        keyring
+            .add_key_from_mnemonic("alice", MNEMONIC_A, COSMOS_BASE_DERIVATION_PATH)
+            .unwrap();
+
+        assert!(keyring.sign_key("alice").is_ok());
+    }
+
+    #[test]
+    fn add_key_from_mnemonic_with_options_matches_explicit_path() {
+        let mut explicit = Keyring::new();
+        explicit
+            .add_key_from_mnemonic("alice", MNEMONIC_A, COSMOS_BASE_DERIVATION_PATH)
+            .unwrap();
+
+        let mut via_options = Keyring::new();
+        via_options
+            .add_key_from_mnemonic_with_options("alice", MNEMONIC_A, 118, 0)
+            .unwrap();
+
+        let explicit_addr = explicit
+            .sign_key("alice")
+            .unwrap()
+            .public_key()
+            .account_id("cosmos")
+            .unwrap();
+        let via_options_addr = via_options
+            .sign_key("alice")
+            .unwrap()
+            .public_key()
+            .account_id("cosmos")
+            .unwrap();
+
+        assert_eq!(explicit_addr, via_options_addr);
+    }
+
+    #[test]
+    fn sign_key_missing_name_errors() {
+        let keyring = Keyring::new();
+        assert!(keyring.sign_key("nobody").is_err());
+    }
+
+    #[test]
+    fn reinserting_a_name_overwrites_the_previous_key() {
+        let mut keyring = Keyring::new();
+        keyring
+            .add_key_from_mnemonic("alice", MNEMONIC_A, COSMOS_BASE_DERIVATION_PATH)
+            .unwrap();
+        let first = keyring
+            .sign_key("alice")
+            .unwrap()
+            .public_key()
+            .account_id("cosmos")
+            .unwrap();
+
+        keyring
+            .add_key_from_mnemonic("alice", MNEMONIC_B, COSMOS_BASE_DERIVATION_PATH)
+            .unwrap();
+        let second = keyring
+            .sign_key("alice")
+            .unwrap()
+            .public_key()
+            .account_id("cosmos")
+            .unwrap();
+
+        assert_ne!(first, second);
+    }
+}
@@ -0,0 +1,89 @@
+use crate::client::middleware::CosmMiddleware;
+use crate::client::nonce::AccountSequence;
+use anyhow::Result;
+use async_trait::async_trait;
+use cosmrs::crypto::secp256k1::SigningKey;
+use cosmrs::tx::{self, Fee};
+use cosmrs::Coin;
+
+/// A source of live gas prices, e.g. polling a chain's fee market module or
+/// a third-party gas station API.
+#[async_trait]
+pub trait GasOracle: Send + Sync {
+    /// Returns the current price to pay per unit of gas, in the chain's
+    /// native denom.
+    async fn gas_price(&self) -> Result<f64>;
+}
+
+/// Wraps an inner layer and replaces its static `cfg.gas_prices` with a
+/// price pulled from `G` on every simulated tx.
+pub struct GasOracleMiddleware<M, G> {
+    inner: M,
+    gas_oracle: G,
+}
+
+impl<M: CosmMiddleware, G: GasOracle> GasOracleMiddleware<M, G> {
+    pub fn new(inner: M, gas_oracle: G) -> Self {
+        Self { inner, gas_oracle }
+    }
+}
+
+#[async_trait]
+impl<M: CosmMiddleware, G: GasOracle> CosmMiddleware for GasOracleMiddleware<M, G> {
+    type Inner = M;
+
+    fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    async fn simulate_gas_fee(
+        &self,
+        tx: &tx::Body,
+        account: AccountSequence,
+        key: &SigningKey,
+    ) -> Result<Fee> {
+        let inner_fee = self.inner().simulate_gas_fee(tx, account, key).await?;
+        let price = self.gas_oracle.gas_price().await?;
+
+        Ok(priced_fee(inner_fee.gas_limit, price, &self.cfg().denom))
+    }
+}
+
+/// Prices `gas_limit` units of gas at `price` per unit, in `denom`, rounding
+/// up so the fee never under-covers what the tx actually used.
+fn priced_fee(gas_limit: u64, price: f64, denom: &str) -> Fee {
+    let amount = Coin {
+        denom: denom.parse().unwrap(),
+        amount: ((gas_limit as f64 * price).ceil() as u64).into(),
+    };
+
+    Fee::from_amount_and_gas(amount, gas_limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn priced_fee_scales_gas_limit_by_price_and_rounds_up() {
+        let fee = priced_fee(100, 1.5, "uatom");
+
+        assert_eq!(fee.gas_limit, 100);
+        assert_eq!(fee.amount[0].amount, 150u64.into());
+        assert_eq!(fee.amount[0].denom.to_string(), "uatom");
+    }
+
+    #[test]
+    fn priced_fee_rounds_fractional_amounts_up() {
+        let fee = priced_fee(100, 0.251, "uatom");
+
+        assert_eq!(fee.amount[0].amount, 26u64.into());
+    }
+
+    #[test]
+    fn priced_fee_with_zero_price_charges_nothing() {
+        let fee = priced_fee(100, 0.0, "uatom");
+
+        assert_eq!(fee.amount[0].amount, 0u64.into());
+    }
+}
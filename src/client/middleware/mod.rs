@@ -0,0 +1,493 @@
+pub mod gas_oracle;
+pub mod nonce_manager;
+pub mod retry;
+
+pub use gas_oracle::{GasOracle, GasOracleMiddleware};
+pub use nonce_manager::NonceManagerMiddleware;
+pub use retry::RetryMiddleware;
+
+use crate::client::cosm_client::{
+    ClearAdminResponse, ExecBatchResponse, ExecResponse, InstantiateResponse, MigrateResponse,
+    QueryResponse, SendResponse, StoreCodeResponse, UpdateAdminResponse,
+};
+use crate::client::keyring::Keyring;
+use crate::client::nonce::AccountSequence;
+use crate::client::tx_options::{BroadcastMode, PollOptions, TxOptions};
+use crate::config::cfg::ChainCfg;
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use cosmrs::bank::MsgSend;
+use cosmrs::cosmwasm::{
+    MsgClearAdmin, MsgExecuteContract, MsgInstantiateContract, MsgMigrateContract, MsgStoreCode,
+    MsgUpdateAdmin,
+};
+use cosmrs::crypto::secp256k1::SigningKey;
+use cosmrs::rpc::endpoint::broadcast::tx_commit::Response;
+use cosmrs::tendermint::abci::tag::Key;
+use cosmrs::tendermint::abci::Event;
+use cosmrs::tendermint::Hash;
+use cosmrs::tx::{self, Fee, Msg, SignDoc, SignerInfo};
+use cosmrs::{AccountId, Any, Coin};
+use std::str::FromStr;
+use std::time::Instant;
+
+/// A layer in a `CosmClient` stack. The base layer (`CosmClient` itself)
+/// talks to the chain over RPC; everything else wraps an inner layer and
+/// overrides only the behavior it wants to change (gas pricing, nonce
+/// caching, retries, ...), delegating the RPC-facing methods (`cfg`, `query`,
+/// `account`, `simulate_gas_fee`, `broadcast_*`, `tx_by_hash`) to `inner()`.
+/// The tx-building methods (`store`, `instantiate`, `execute`, ...,
+/// `send_tx`) are not delegated: they run on the layer they are called on and
+/// reach the chain through that layer's RPC-facing methods, so a `send_tx`
+/// override in an inner layer (as in `NonceManagerMiddleware`) is bypassed
+/// once another layer wraps it. Mirrors
+/// the provider/middleware stacking used by ethers-rs, so cross-cutting
+/// behavior can be composed by wrapping rather than forking the client.
+#[async_trait]
+pub trait CosmMiddleware: Send + Sync {
+    type Inner: CosmMiddleware;
+
+    fn inner(&self) -> &Self::Inner;
+
+    fn cfg(&self) -> &ChainCfg {
+        self.inner().cfg()
+    }
+
+    /// Looks up `key_name` in `keyring`, so `store`/`instantiate`/`execute`/
+    /// etc. can be invoked by a caller-chosen key name instead of holding a
+    /// `SigningKey` directly, e.g. `client.store(payload, client.key(&keyring, "validator")?, tx_options)`.
+    fn key<'a>(&self, keyring: &'a Keyring, key_name: &str) -> Result<&'a SigningKey> {
+        keyring.sign_key(key_name)
+    }
+
+    async fn store(
+        &self,
+        payload: Vec<u8>,
+        signing_key: &SigningKey,
+        tx_options: TxOptions,
+    ) -> Result<StoreCodeResponse> {
+        let sender_account_id = signing_key
+            .public_key()
+            .account_id(&self.cfg().prefix)
+            .unwrap();
+
+        let msg = MsgStoreCode {
+            sender: sender_account_id.clone(),
+            wasm_byte_code: payload,
+            instantiate_permission: None,
+        }
+        .to_any()
+        .unwrap();
+
+        let tx_res = self
+            .send_tx(vec![msg], signing_key, sender_account_id, tx_options)
+            .await?;
+
+        let res = find_event(&tx_res, "store_code").context("error storing code")?;
+        let code_id = res
+            .attributes
+            .iter()
+            .find(|a| a.key == Key::from_str("code_id").unwrap())
+            .unwrap()
+            .value
+            .as_ref()
+            .parse::<u64>()?;
+
+        Ok(StoreCodeResponse {
+            code_id,
+            data: tx_res.deliver_tx,
+        })
+    }
+
+    async fn instantiate(
+        &self,
+        code_id: u64,
+        payload: Vec<u8>,
+        signing_key: &SigningKey,
+        funds: Vec<Coin>,
+        admin: Option<String>,
+        tx_options: TxOptions,
+    ) -> Result<InstantiateResponse> {
+        let sender_account_id = signing_key
+            .public_key()
+            .account_id(&self.cfg().prefix)
+            .unwrap();
+
+        let msg = MsgInstantiateContract {
+            sender: sender_account_id.clone(),
+            admin: admin.map(|a| a.parse()).transpose()?,
+            code_id,
+            label: Some("cosm-orc".to_string()),
+            msg: payload,
+            funds,
+        }
+        .to_any()
+        .unwrap();
+
+        let tx_res = self
+            .send_tx(vec![msg], signing_key, sender_account_id, tx_options)
+            .await?;
+
+        let res = find_event(&tx_res, "instantiate").context("error instantiating code")?;
+        let addr = res
+            .attributes
+            .iter()
+            .find(|a| a.key == Key::from_str("_contract_address").unwrap())
+            .unwrap()
+            .value
+            .to_string();
+
+        Ok(InstantiateResponse {
+            address: addr,
+            data: tx_res.deliver_tx,
+        })
+    }
+
+    async fn execute(
+        &self,
+        address: String,
+        payload: Vec<u8>,
+        signing_key: &SigningKey,
+        funds: Vec<Coin>,
+        tx_options: TxOptions,
+    ) -> Result<ExecResponse> {
+        let sender_account_id = signing_key
+            .public_key()
+            .account_id(&self.cfg().prefix)
+            .unwrap();
+
+        let msg = MsgExecuteContract {
+            sender: sender_account_id.clone(),
+            contract: address.parse().unwrap(),
+            msg: payload,
+            funds,
+        }
+        .to_any()
+        .unwrap();
+
+        let tx_res = self
+            .send_tx(vec![msg], signing_key, sender_account_id, tx_options)
+            .await?;
+
+        Ok(ExecResponse {
+            data: tx_res.deliver_tx,
+        })
+    }
+
+    /// Executes several contracts in one atomic tx — a simulate-once,
+    /// sign-once, broadcast-once alternative to calling `execute` per
+    /// contract, for orchestrating dependent contract calls.
+    async fn execute_batch(
+        &self,
+        msgs: Vec<(String, Vec<u8>, Vec<Coin>)>,
+        signing_key: &SigningKey,
+        tx_options: TxOptions,
+    ) -> Result<ExecBatchResponse> {
+        let sender_account_id = signing_key
+            .public_key()
+            .account_id(&self.cfg().prefix)
+            .unwrap();
+
+        let mut any_msgs = Vec::with_capacity(msgs.len());
+        for (address, payload, funds) in msgs {
+            any_msgs.push(
+                MsgExecuteContract {
+                    sender: sender_account_id.clone(),
+                    contract: address.parse().unwrap(),
+                    msg: payload,
+                    funds,
+                }
+                .to_any()
+                .unwrap(),
+            );
+        }
+
+        let tx_res = self
+            .send_tx(any_msgs, signing_key, sender_account_id, tx_options)
+            .await?;
+
+        Ok(ExecBatchResponse {
+            data: tx_res.deliver_tx,
+        })
+    }
+
+    async fn send(
+        &self,
+        recipient: AccountId,
+        amount: Vec<Coin>,
+        signing_key: &SigningKey,
+        tx_options: TxOptions,
+    ) -> Result<SendResponse> {
+        let sender_account_id = signing_key
+            .public_key()
+            .account_id(&self.cfg().prefix)
+            .unwrap();
+
+        let msg = MsgSend {
+            from_address: sender_account_id.clone(),
+            to_address: recipient,
+            amount,
+        }
+        .to_any()
+        .unwrap();
+
+        let tx_res = self
+            .send_tx(vec![msg], signing_key, sender_account_id, tx_options)
+            .await?;
+
+        Ok(SendResponse {
+            data: tx_res.deliver_tx,
+        })
+    }
+
+    async fn migrate(
+        &self,
+        address: String,
+        new_code_id: u64,
+        payload: Vec<u8>,
+        signing_key: &SigningKey,
+        tx_options: TxOptions,
+    ) -> Result<MigrateResponse> {
+        let sender_account_id = signing_key
+            .public_key()
+            .account_id(&self.cfg().prefix)
+            .unwrap();
+
+        let msg = MsgMigrateContract {
+            sender: sender_account_id.clone(),
+            contract: address.parse().unwrap(),
+            code_id: new_code_id,
+            msg: payload,
+        }
+        .to_any()
+        .unwrap();
+
+        let tx_res = self
+            .send_tx(vec![msg], signing_key, sender_account_id, tx_options)
+            .await?;
+
+        let res = find_event(&tx_res, "migrate").context("error migrating code")?;
+        let code_id = res
+            .attributes
+            .iter()
+            .find(|a| a.key == Key::from_str("code_id").unwrap())
+            .unwrap()
+            .value
+            .as_ref()
+            .parse::<u64>()?;
+
+        Ok(MigrateResponse {
+            code_id,
+            data: tx_res.deliver_tx,
+        })
+    }
+
+    async fn update_admin(
+        &self,
+        address: String,
+        new_admin: String,
+        signing_key: &SigningKey,
+        tx_options: TxOptions,
+    ) -> Result<UpdateAdminResponse> {
+        let sender_account_id = signing_key
+            .public_key()
+            .account_id(&self.cfg().prefix)
+            .unwrap();
+
+        let msg = MsgUpdateAdmin {
+            sender: sender_account_id.clone(),
+            new_admin: new_admin.parse().unwrap(),
+            contract: address.parse().unwrap(),
+        }
+        .to_any()
+        .unwrap();
+
+        let tx_res = self
+            .send_tx(vec![msg], signing_key, sender_account_id, tx_options)
+            .await?;
+
+        Ok(UpdateAdminResponse {
+            data: tx_res.deliver_tx,
+        })
+    }
+
+    async fn clear_admin(
+        &self,
+        address: String,
+        signing_key: &SigningKey,
+        tx_options: TxOptions,
+    ) -> Result<ClearAdminResponse> {
+        let sender_account_id = signing_key
+            .public_key()
+            .account_id(&self.cfg().prefix)
+            .unwrap();
+
+        let msg = MsgClearAdmin {
+            sender: sender_account_id.clone(),
+            contract: address.parse().unwrap(),
+        }
+        .to_any()
+        .unwrap();
+
+        let tx_res = self
+            .send_tx(vec![msg], signing_key, sender_account_id, tx_options)
+            .await?;
+
+        Ok(ClearAdminResponse {
+            data: tx_res.deliver_tx,
+        })
+    }
+
+    /// Signs, simulates, and broadcasts arbitrary `msgs` as a single atomic
+    /// tx on behalf of `signing_key`. `store`/`instantiate`/`execute`/... are
+    /// thin wrappers around this for the common single-message case; reach
+    /// for this directly to bundle several message types together.
+    async fn send_msgs(
+        &self,
+        msgs: Vec<Any>,
+        signing_key: &SigningKey,
+        tx_options: TxOptions,
+    ) -> Result<Response> {
+        let sender_account_id = signing_key
+            .public_key()
+            .account_id(&self.cfg().prefix)
+            .unwrap();
+
+        self.send_tx(msgs, signing_key, sender_account_id, tx_options)
+            .await
+    }
+
+    async fn query(&self, address: String, payload: Vec<u8>) -> Result<QueryResponse> {
+        self.inner().query(address, payload).await
+    }
+
+    /// Looks up the `account_number`/`sequence` to sign the next tx from
+    /// `account_id` with.
+    async fn account(&self, account_id: AccountId) -> Result<AccountSequence> {
+        self.inner().account(account_id).await
+    }
+
+    /// Estimates the gas a tx will use and prices it into a `Fee`.
+    async fn simulate_gas_fee(
+        &self,
+        tx: &tx::Body,
+        account: AccountSequence,
+        key: &SigningKey,
+    ) -> Result<Fee> {
+        self.inner().simulate_gas_fee(tx, account, key).await
+    }
+
+    /// Broadcasts a signed tx and waits for it to be committed to a block.
+    /// Only transport-level failures are surfaced here; `send_tx` is the one
+    /// that turns a failing `check_tx`/`deliver_tx` code into an error, so
+    /// middleware layers can inspect the result first (e.g. to retry on a
+    /// sequence mismatch).
+    async fn broadcast_commit(&self, tx_bytes: Vec<u8>) -> Result<Response> {
+        self.inner().broadcast_commit(tx_bytes).await
+    }
+
+    /// Broadcasts a signed tx, returning as soon as `check_tx` passes
+    /// instead of waiting for it to land in a block.
+    async fn broadcast_sync(&self, tx_bytes: Vec<u8>) -> Result<Hash> {
+        self.inner().broadcast_sync(tx_bytes).await
+    }
+
+    /// Broadcasts a signed tx, returning as soon as it's accepted into the
+    /// mempool without running `check_tx` at all.
+    async fn broadcast_async(&self, tx_bytes: Vec<u8>) -> Result<Hash> {
+        self.inner().broadcast_async(tx_bytes).await
+    }
+
+    /// Looks up a previously broadcast tx by hash, for use while polling a
+    /// `Sync`/`Async` broadcast to completion. Returns `None` while the tx
+    /// hasn't landed in a block yet.
+    async fn tx_by_hash(&self, hash: Hash) -> Result<Option<Response>> {
+        self.inner().tx_by_hash(hash).await
+    }
+
+    /// Polls `tx_by_hash` until `hash` lands in a block or `poll.timeout`
+    /// elapses.
+    async fn poll_for_tx(&self, hash: Hash, poll: PollOptions) -> Result<Response> {
+        let deadline = Instant::now() + poll.timeout;
+        loop {
+            if let Some(res) = self.tx_by_hash(hash).await? {
+                return Ok(res);
+            }
+            if Instant::now() >= deadline {
+                bail!("timed out after {:?} waiting for tx {hash} to land in a block", poll.timeout);
+            }
+            tokio::time::sleep(poll.interval).await;
+        }
+    }
+
+    /// Signs, simulates, and broadcasts `msgs` as a single tx according to
+    /// `tx_options`.
+    async fn send_tx(
+        &self,
+        msgs: Vec<Any>,
+        key: &SigningKey,
+        account_id: AccountId,
+        tx_options: TxOptions,
+    ) -> Result<Response> {
+        let account = self.account(account_id.clone()).await?;
+
+        let timeout_height = tx_options.timeout_height.unwrap_or(0);
+        let memo = tx_options.memo.as_deref().unwrap_or("MEMO");
+        let tx_body = tx::Body::new(msgs, memo, timeout_height);
+
+        let fee = match tx_options.fee {
+            Some(fee) => fee,
+            None => self.simulate_gas_fee(&tx_body, account, key).await?,
+        };
+
+        let auth_info =
+            SignerInfo::single_direct(Some(key.public_key()), account.sequence).auth_info(fee);
+        let sign_doc = SignDoc::new(
+            &tx_body,
+            &auth_info,
+            &self.cfg().chain_id.parse()?,
+            account.account_number,
+        )
+        .unwrap();
+        let tx_raw = sign_doc.sign(key).unwrap();
+        let tx_bytes = tx_raw.to_bytes().unwrap();
+
+        match tx_options.broadcast_mode {
+            BroadcastMode::Block => {
+                let res = self.broadcast_commit(tx_bytes).await?;
+                check_tx_codes(&res)?;
+                Ok(res)
+            }
+            BroadcastMode::Sync(poll) => {
+                let hash = self.broadcast_sync(tx_bytes).await?;
+                let res = self.poll_for_tx(hash, poll).await?;
+                check_tx_codes(&res)?;
+                Ok(res)
+            }
+            BroadcastMode::Async(poll) => {
+                let hash = self.broadcast_async(tx_bytes).await?;
+                let res = self.poll_for_tx(hash, poll).await?;
+                check_tx_codes(&res)?;
+                Ok(res)
+            }
+        }
+    }
+}
+
+fn check_tx_codes(res: &Response) -> Result<()> {
+    if res.check_tx.code.is_err() {
+        bail!("check_tx failed: {:?}", res.check_tx)
+    }
+    if res.deliver_tx.code.is_err() {
+        bail!("deliver_tx failed: {:?}", res.deliver_tx);
+    }
+    Ok(())
+}
+
+pub(crate) fn find_event(res: &Response, key_name: &str) -> Option<Event> {
+    res.deliver_tx
+        .events
+        .iter()
+        .find(|e| e.type_str == key_name)
+        .cloned()
+}
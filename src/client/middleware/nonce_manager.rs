@@ -0,0 +1,261 @@
+use crate::client::middleware::CosmMiddleware;
+use crate::client::nonce::{is_sequence_mismatch, AccountSequence, NonceManager};
+use crate::client::tx_options::{BroadcastMode, TxOptions};
+use anyhow::{bail, Result};
+use async_trait::async_trait;
+use cosmrs::crypto::secp256k1::SigningKey;
+use cosmrs::cosmwasm::MsgExecuteContract;
+use cosmrs::rpc::endpoint::broadcast::tx_commit::Response;
+use cosmrs::tendermint::Hash;
+use cosmrs::tx::{self, Msg, SignDoc, SignerInfo};
+use cosmrs::{AccountId, Any, Coin};
+use std::fmt;
+
+/// Wraps an inner layer with a cached `account_number`/`sequence` per
+/// account, so scripting many txs from the same key doesn't pay for an
+/// `/auth/Account` query before every single one. Each `account()` call
+/// reserves its sequence number up front (see `NonceManager::next_sequence`),
+/// so concurrent callers for the same account never sign with the same
+/// sequence; a reservation that fails before reaching the chain is handed
+/// back via `rollback`. When `send_tx` gets a sequence-mismatch (`code 32`)
+/// `check_tx` result back from a `Block` broadcast, the account's cache entry
+/// is dropped and the tx is signed and sent once more after a resync from
+/// chain. In `Sync` mode and in `execute_fast_batch`, a `check_tx` rejection
+/// arrives as a `broadcast_sync` error with no code to inspect, so a
+/// mismatch there only rolls the reservation back and the cache entry is
+/// kept.
+pub struct NonceManagerMiddleware<M> {
+    inner: M,
+    nonce_manager: NonceManager,
+}
+
+/// Error from `execute_fast_batch`: each message is broadcast independently,
+/// so a failure partway through doesn't unwind anything already sent to the
+/// chain. `broadcast` carries the hashes of those already-broadcast txs so
+/// the caller can still look them up instead of losing track of them.
+#[derive(Debug)]
+pub struct FastBatchError {
+    pub broadcast: Vec<Hash>,
+    pub source: anyhow::Error,
+}
+
+impl fmt::Display for FastBatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} ({} tx(es) were already broadcast before this failure: {:?})",
+            self.source,
+            self.broadcast.len(),
+            self.broadcast
+        )
+    }
+}
+
+impl std::error::Error for FastBatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.source.source()
+    }
+}
+
+impl<M: CosmMiddleware> NonceManagerMiddleware<M> {
+    pub fn new(inner: M) -> Self {
+        Self {
+            inner,
+            nonce_manager: NonceManager::new(),
+        }
+    }
+
+    /// Fires one `execute` per `(address, payload, funds)` tuple back to
+    /// back against the same signer, signing and broadcasting the next tx
+    /// as soon as the previous one passes `check_tx` rather than waiting for
+    /// it to commit. This trades per-tx confirmation for throughput, so
+    /// it's meant for scripting many independent txs from the same key
+    /// (e.g. deployment scripts) rather than flows that need each tx's
+    /// result. `tx_options.broadcast_mode` is ignored since this path always
+    /// broadcasts sync; the memo/timeout_height/fee overrides still apply to
+    /// every message in the batch.
+    ///
+    /// A failure partway through doesn't roll back earlier messages in the
+    /// batch — they were genuinely broadcast — so the error carries their
+    /// hashes (see `FastBatchError`) instead of dropping them.
+    pub async fn execute_fast_batch(
+        &self,
+        msgs: Vec<(String, Vec<u8>, Vec<Coin>)>,
+        signing_key: &SigningKey,
+        tx_options: TxOptions,
+    ) -> Result<Vec<Hash>, FastBatchError> {
+        let sender_account_id = signing_key
+            .public_key()
+            .account_id(&self.cfg().prefix)
+            .unwrap();
+
+        let mut hashes = Vec::with_capacity(msgs.len());
+        for (address, payload, funds) in msgs {
+            let msg = MsgExecuteContract {
+                sender: sender_account_id.clone(),
+                contract: address.parse().unwrap(),
+                msg: payload,
+                funds,
+            }
+            .to_any()
+            .unwrap();
+
+            let hash = self
+                .send_tx_no_wait(msg, signing_key, sender_account_id.clone(), &tx_options)
+                .await
+                .map_err(|source| FastBatchError {
+                    broadcast: hashes.clone(),
+                    source,
+                })?;
+            hashes.push(hash);
+        }
+
+        Ok(hashes)
+    }
+
+    async fn send_tx_no_wait(
+        &self,
+        msg: Any,
+        key: &SigningKey,
+        account_id: AccountId,
+        tx_options: &TxOptions,
+    ) -> Result<Hash> {
+        let account = self.account(account_id.clone()).await?;
+
+        let timeout_height = tx_options.timeout_height.unwrap_or(0);
+        let memo = tx_options.memo.as_deref().unwrap_or("MEMO");
+        let tx_body = tx::Body::new(vec![msg], memo, timeout_height);
+
+        // `account` already reserved this sequence in `self.account()` above;
+        // give it back if we fail before anything reaches the chain, so the
+        // next call doesn't skip a sequence number.
+        let result: Result<Hash> = async {
+            let fee = match tx_options.fee.clone() {
+                Some(fee) => fee,
+                None => self.simulate_gas_fee(&tx_body, account, key).await?,
+            };
+
+            let auth_info = SignerInfo::single_direct(Some(key.public_key()), account.sequence)
+                .auth_info(fee);
+            let sign_doc = SignDoc::new(
+                &tx_body,
+                &auth_info,
+                &self.cfg().chain_id.parse()?,
+                account.account_number,
+            )
+            .unwrap();
+            let tx_raw = sign_doc.sign(key).unwrap();
+
+            self.broadcast_sync(tx_raw.to_bytes().unwrap()).await
+        }
+        .await;
+
+        if result.is_err() {
+            self.nonce_manager.rollback(&account_id, account).await;
+        }
+        result
+    }
+}
+
+#[async_trait]
+impl<M: CosmMiddleware> CosmMiddleware for NonceManagerMiddleware<M> {
+    type Inner = M;
+
+    fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    async fn account(&self, account_id: AccountId) -> Result<AccountSequence> {
+        let inner = &self.inner;
+        self.nonce_manager
+            .next_sequence(&account_id, || inner.account(account_id.clone()))
+            .await
+    }
+
+    async fn send_tx(
+        &self,
+        msgs: Vec<Any>,
+        key: &SigningKey,
+        account_id: AccountId,
+        tx_options: TxOptions,
+    ) -> Result<Response> {
+        for attempt in 0..2 {
+            let account = self.account(account_id.clone()).await?;
+
+            // `account` already reserved this sequence in `self.account()`
+            // above; give it back if we fail before anything reaches the
+            // chain, so the next call doesn't skip a sequence number. A
+            // `Sync` broadcast that returned has passed `check_tx` and sits
+            // in the mempool, so a later polling failure keeps the
+            // reservation.
+            let mut accepted_by_mempool = false;
+            let result: Result<Response> = async {
+                let timeout_height = tx_options.timeout_height.unwrap_or(0);
+                let memo = tx_options.memo.as_deref().unwrap_or("MEMO");
+                let tx_body = tx::Body::new(msgs.clone(), memo, timeout_height);
+
+                let fee = match tx_options.fee.clone() {
+                    Some(fee) => fee,
+                    None => self.simulate_gas_fee(&tx_body, account, key).await?,
+                };
+
+                let auth_info =
+                    SignerInfo::single_direct(Some(key.public_key()), account.sequence)
+                        .auth_info(fee);
+                let sign_doc = SignDoc::new(
+                    &tx_body,
+                    &auth_info,
+                    &self.cfg().chain_id.parse()?,
+                    account.account_number,
+                )
+                .unwrap();
+                let tx_raw = sign_doc.sign(key).unwrap();
+                let tx_bytes = tx_raw.to_bytes().unwrap();
+
+                match tx_options.broadcast_mode {
+                    BroadcastMode::Block => self.broadcast_commit(tx_bytes).await,
+                    BroadcastMode::Sync(poll) => {
+                        let hash = self.broadcast_sync(tx_bytes).await?;
+                        accepted_by_mempool = true;
+                        self.poll_for_tx(hash, poll).await
+                    }
+                    BroadcastMode::Async(poll) => {
+                        let hash = self.broadcast_async(tx_bytes).await?;
+                        self.poll_for_tx(hash, poll).await
+                    }
+                }
+            }
+            .await;
+
+            let res = match result {
+                Ok(res) => res,
+                Err(err) => {
+                    if !accepted_by_mempool {
+                        self.nonce_manager.rollback(&account_id, account).await;
+                    }
+                    return Err(err);
+                }
+            };
+
+            if res.check_tx.code.is_err() {
+                if attempt == 0 && is_sequence_mismatch(res.check_tx.code) {
+                    self.nonce_manager.invalidate(&account_id).await;
+                    continue;
+                }
+                // check_tx rejected the tx before it reached a block, so the
+                // reservation above was never consumed on-chain — give it
+                // back, unlike a deliver_tx failure below where the sequence
+                // really was spent.
+                self.nonce_manager.rollback(&account_id, account).await;
+                bail!("check_tx failed: {:?}", res.check_tx)
+            }
+            if res.deliver_tx.code.is_err() {
+                bail!("deliver_tx failed: {:?}", res.deliver_tx);
+            }
+
+            return Ok(res);
+        }
+
+        unreachable!("loop either returns or bails within its two attempts")
+    }
+}
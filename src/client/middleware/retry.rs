@@ -0,0 +1,332 @@
+use crate::client::cosm_client::QueryResponse;
+use crate::client::middleware::CosmMiddleware;
+use crate::client::nonce::AccountSequence;
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use cosmrs::crypto::secp256k1::SigningKey;
+use cosmrs::rpc::endpoint::broadcast::tx_commit::Response;
+use cosmrs::tendermint::abci::Code;
+use cosmrs::tendermint::Hash;
+use cosmrs::tx::{self, Fee};
+use cosmrs::AccountId;
+use rand::Rng;
+use std::future::Future;
+use std::time::Duration;
+use tokio::sync::{Mutex, Semaphore};
+use tokio::time::Instant;
+
+/// ABCI codes worth retrying rather than treating as a terminal failure.
+/// Currently just the Cosmos SDK's `ErrMempoolIsFull`.
+const RECOVERABLE_CODES: &[u32] = &[20];
+
+/// Retry/backoff and rate-limiting knobs for `RetryMiddleware`.
+#[derive(Debug, Clone)]
+pub struct RetryConfig {
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub backoff_factor: f64,
+    /// Fraction of the computed delay to randomize, in `[0.0, 1.0]`.
+    pub jitter: f64,
+    /// Caps the number of RPC calls in flight at once.
+    pub max_in_flight: Option<usize>,
+    /// Caps the rate of RPC calls, spacing them out to stay under it.
+    pub max_requests_per_sec: Option<f64>,
+}
+
+impl Default for RetryConfig {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            base_delay: Duration::from_millis(200),
+            backoff_factor: 2.0,
+            jitter: 0.1,
+            max_in_flight: None,
+            max_requests_per_sec: None,
+        }
+    }
+}
+
+/// Wraps an inner layer and retries its RPC-facing calls (`broadcast_commit`,
+/// `broadcast_sync`, `broadcast_async`, `tx_by_hash`, `query`, `account`,
+/// `simulate_gas_fee`) with exponential backoff, optionally capping
+/// in-flight/per-second request rates so public or throttled RPC endpoints
+/// don't get hammered. `broadcast_commit` retries transport-level failures
+/// and `check_tx` results with a recoverable ABCI code; the other calls
+/// retry on any error the inner layer returns, which for `broadcast_sync`
+/// includes a `check_tx` rejection of any code.
+/// Covering `broadcast_sync`/`broadcast_async`/`tx_by_hash` matters as much
+/// as `broadcast_commit` — `poll_for_tx` calls `tx_by_hash` in a tight loop
+/// for `Sync`/`Async` broadcast modes, and that loop needs the same
+/// rate-limiting as everything else or it defeats the point of this layer.
+pub struct RetryMiddleware<M> {
+    inner: M,
+    cfg: RetryConfig,
+    limiter: RateLimiter,
+}
+
+impl<M: CosmMiddleware> RetryMiddleware<M> {
+    pub fn new(inner: M, cfg: RetryConfig) -> Self {
+        let limiter = RateLimiter::new(&cfg);
+        Self { inner, cfg, limiter }
+    }
+
+    fn delay_for(&self, attempt: u32) -> Duration {
+        let scaled = self.cfg.base_delay.mul_f64(self.cfg.backoff_factor.powi(attempt as i32));
+        jittered(scaled, self.cfg.jitter)
+    }
+
+    /// Retries a plain RPC call (rate-limited the same as `broadcast_commit`
+    /// and `query`) on any error it returns, transport-level or not. `broadcast_commit` has
+    /// its own loop since it also treats certain `Ok` responses as
+    /// recoverable.
+    async fn retry<T, F, Fut>(&self, f: F) -> Result<T>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T>>,
+    {
+        let mut last_err = None;
+
+        for attempt in 0..self.cfg.max_attempts.max(1) {
+            let _permit = self.limiter.acquire().await;
+
+            match f().await {
+                Ok(val) => return Ok(val),
+                Err(err) => last_err = Some(err),
+            }
+
+            if attempt + 1 < self.cfg.max_attempts.max(1) {
+                tokio::time::sleep(self.delay_for(attempt)).await;
+            }
+        }
+
+        Err(last_err.expect("max_attempts.max(1) always runs at least once"))
+    }
+}
+
+fn jittered(delay: Duration, jitter: f64) -> Duration {
+    let jitter = jitter.clamp(0.0, 1.0);
+    if jitter == 0.0 {
+        return delay;
+    }
+    let factor = 1.0 + rand::thread_rng().gen_range(-jitter..=jitter);
+    delay.mul_f64(factor.max(0.0))
+}
+
+fn is_recoverable(code: Code) -> bool {
+    matches!(code, Code::Err(c) if RECOVERABLE_CODES.contains(&c.get()))
+}
+
+/// Caps how many RPC calls can be in flight and/or issued per second.
+struct RateLimiter {
+    in_flight: Option<Semaphore>,
+    min_interval: Option<Duration>,
+    last_request: Mutex<Instant>,
+}
+
+impl RateLimiter {
+    fn new(cfg: &RetryConfig) -> Self {
+        Self {
+            in_flight: cfg.max_in_flight.map(Semaphore::new),
+            min_interval: cfg
+                .max_requests_per_sec
+                .map(|rps| Duration::from_secs_f64(1.0 / rps)),
+            last_request: Mutex::new(Instant::now()),
+        }
+    }
+
+    #[cfg(test)]
+    fn in_flight_available_permits(&self) -> Option<usize> {
+        self.in_flight.as_ref().map(Semaphore::available_permits)
+    }
+
+    /// Blocks until the call is allowed to proceed, honoring both the
+    /// in-flight cap (held until the returned guard drops) and the
+    /// per-second cap.
+    async fn acquire(&self) -> Option<tokio::sync::SemaphorePermit<'_>> {
+        let permit = match &self.in_flight {
+            Some(sem) => Some(sem.acquire().await.expect("semaphore is never closed")),
+            None => None,
+        };
+
+        if let Some(min_interval) = self.min_interval {
+            let mut last_request = self.last_request.lock().await;
+            let elapsed = last_request.elapsed();
+            if elapsed < min_interval {
+                tokio::time::sleep(min_interval - elapsed).await;
+            }
+            *last_request = Instant::now();
+        }
+
+        permit
+    }
+}
+
+#[async_trait]
+impl<M: CosmMiddleware> CosmMiddleware for RetryMiddleware<M> {
+    type Inner = M;
+
+    fn inner(&self) -> &M {
+        &self.inner
+    }
+
+    async fn broadcast_commit(&self, tx_bytes: Vec<u8>) -> Result<Response> {
+        let mut last_err = None;
+
+        for attempt in 0..self.cfg.max_attempts.max(1) {
+            let _permit = self.limiter.acquire().await;
+
+            match self.inner().broadcast_commit(tx_bytes.clone()).await {
+                Ok(res) if !is_recoverable(res.check_tx.code) => return Ok(res),
+                Ok(res) => last_err = Some(anyhow!("recoverable check_tx failure: {:?}", res.check_tx)),
+                Err(err) => last_err = Some(err),
+            }
+
+            if attempt + 1 < self.cfg.max_attempts.max(1) {
+                tokio::time::sleep(self.delay_for(attempt)).await;
+            }
+        }
+
+        Err(last_err.expect("max_attempts.max(1) always runs at least once"))
+    }
+
+    async fn broadcast_sync(&self, tx_bytes: Vec<u8>) -> Result<Hash> {
+        self.retry(|| self.inner().broadcast_sync(tx_bytes.clone()))
+            .await
+    }
+
+    async fn broadcast_async(&self, tx_bytes: Vec<u8>) -> Result<Hash> {
+        self.retry(|| self.inner().broadcast_async(tx_bytes.clone()))
+            .await
+    }
+
+    async fn tx_by_hash(&self, hash: Hash) -> Result<Option<Response>> {
+        self.retry(|| self.inner().tx_by_hash(hash)).await
+    }
+
+    async fn query(&self, address: String, payload: Vec<u8>) -> Result<QueryResponse> {
+        self.retry(|| self.inner().query(address.clone(), payload.clone()))
+            .await
+    }
+
+    async fn account(&self, account_id: AccountId) -> Result<AccountSequence> {
+        self.retry(|| self.inner().account(account_id.clone())).await
+    }
+
+    async fn simulate_gas_fee(
+        &self,
+        tx: &tx::Body,
+        account: AccountSequence,
+        key: &SigningKey,
+    ) -> Result<Fee> {
+        self.retry(|| self.inner().simulate_gas_fee(tx, account, key))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::num::NonZeroU32;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// A `CosmMiddleware` that's never actually called — just enough to give
+    /// `RetryMiddleware<M>` a concrete `M` so its private `retry`/rate-limiting
+    /// helpers can be exercised directly, without a real RPC-backed layer.
+    struct NoopInner;
+
+    #[async_trait]
+    impl CosmMiddleware for NoopInner {
+        type Inner = Self;
+
+        fn inner(&self) -> &Self {
+            self
+        }
+    }
+
+    fn middleware(cfg: RetryConfig) -> RetryMiddleware<NoopInner> {
+        RetryMiddleware::new(NoopInner, cfg)
+    }
+
+    #[test]
+    fn jittered_with_zero_jitter_is_unchanged() {
+        let delay = Duration::from_millis(100);
+        assert_eq!(jittered(delay, 0.0), delay);
+    }
+
+    #[test]
+    fn jittered_stays_within_the_configured_fraction() {
+        let base = Duration::from_millis(100);
+        for _ in 0..100 {
+            let delay = jittered(base, 0.2);
+            assert!(
+                delay >= Duration::from_millis(80) && delay <= Duration::from_millis(120),
+                "{delay:?} is outside +/-20% of {base:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn is_recoverable_matches_only_the_known_codes() {
+        assert!(is_recoverable(Code::Err(NonZeroU32::new(20).unwrap())));
+        assert!(!is_recoverable(Code::Err(NonZeroU32::new(1).unwrap())));
+        assert!(!is_recoverable(Code::Ok));
+    }
+
+    #[tokio::test]
+    async fn retry_returns_as_soon_as_the_call_succeeds() {
+        let mw = middleware(RetryConfig {
+            base_delay: Duration::ZERO,
+            ..RetryConfig::default()
+        });
+        let attempts = AtomicU32::new(0);
+
+        let result = mw
+            .retry(|| async {
+                if attempts.fetch_add(1, Ordering::SeqCst) < 2 {
+                    Err(anyhow!("still failing"))
+                } else {
+                    Ok(attempts.load(Ordering::SeqCst))
+                }
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(result, 3);
+    }
+
+    #[tokio::test]
+    async fn retry_gives_up_after_max_attempts() {
+        let mw = middleware(RetryConfig {
+            max_attempts: 3,
+            base_delay: Duration::ZERO,
+            ..RetryConfig::default()
+        });
+        let attempts = AtomicU32::new(0);
+
+        let result: Result<()> = mw
+            .retry(|| async {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                Err(anyhow!("nope"))
+            })
+            .await;
+
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn rate_limiter_caps_in_flight_permits_until_released() {
+        let cfg = RetryConfig {
+            max_in_flight: Some(2),
+            ..RetryConfig::default()
+        };
+        let limiter = RateLimiter::new(&cfg);
+        assert_eq!(limiter.in_flight_available_permits(), Some(2));
+
+        let permit = limiter.acquire().await;
+        assert_eq!(limiter.in_flight_available_permits(), Some(1));
+
+        drop(permit);
+        assert_eq!(limiter.in_flight_available_permits(), Some(2));
+    }
+}
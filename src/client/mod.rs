@@ -0,0 +1,5 @@
+pub mod cosm_client;
+pub mod keyring;
+pub mod middleware;
+pub mod nonce;
+pub mod tx_options;
@@ -0,0 +1,217 @@
+use anyhow::Result;
+use cosmrs::tendermint::abci::Code;
+use cosmrs::AccountId;
+use std::collections::HashMap;
+use std::future::Future;
+use tokio::sync::Mutex;
+
+/// The ABCI code the auth module returns when a tx is signed with a stale
+/// `sequence` number.
+pub const SEQUENCE_MISMATCH_CODE: u32 = 32;
+
+/// Whether `code` is the auth module's sequence-mismatch error, i.e. whether
+/// retrying with a resynced `sequence` is worth attempting.
+pub fn is_sequence_mismatch(code: Code) -> bool {
+    matches!(code, Code::Err(c) if c.get() == SEQUENCE_MISMATCH_CODE)
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct AccountSequence {
+    pub account_number: u64,
+    pub sequence: u64,
+}
+
+/// Caches each account's `account_number` / `sequence` pair so scripting many
+/// txs from the same key doesn't pay for an `/auth/Account` query before
+/// every single one. `next_sequence` reserves (pre-increments) the cached
+/// sequence before the caller signs and broadcasts, so two concurrent
+/// callers for the same account are handed distinct sequence numbers
+/// instead of racing to read-then-advance after their own round trip
+/// completes. A reservation that never reaches the chain can be given back
+/// with `rollback`, and the whole cache entry is dropped on a
+/// sequence-mismatch (`code 32`) error, forcing a resync from chain on the
+/// next lookup.
+#[derive(Default)]
+pub struct NonceManager {
+    cache: Mutex<HashMap<AccountId, AccountSequence>>,
+}
+
+impl NonceManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the sequence to sign the next tx from `account_id` with,
+    /// calling `fetch` to populate the cache on first use, and reserves the
+    /// following sequence for whoever calls next. The read-then-reserve
+    /// step happens while holding the cache lock, so two concurrent callers
+    /// for the same account always get distinct sequence numbers rather
+    /// than both reading the same not-yet-advanced one.
+    pub async fn next_sequence<F, Fut>(
+        &self,
+        account_id: &AccountId,
+        fetch: F,
+    ) -> Result<AccountSequence>
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = Result<AccountSequence>>,
+    {
+        let mut cache = self.cache.lock().await;
+        let acc = match cache.get(account_id) {
+            Some(acc) => *acc,
+            None => fetch().await?,
+        };
+        cache.insert(
+            account_id.clone(),
+            AccountSequence {
+                sequence: acc.sequence + 1,
+                ..acc
+            },
+        );
+        Ok(acc)
+    }
+
+    /// Releases a reservation made by `next_sequence` when the tx that would
+    /// have used it never reached the chain (e.g. signing or broadcasting
+    /// failed before anything was sent), so the next caller doesn't skip a
+    /// sequence number. A no-op if a later reservation already moved the
+    /// cached sequence past `reserved`.
+    pub async fn rollback(&self, account_id: &AccountId, reserved: AccountSequence) {
+        let mut cache = self.cache.lock().await;
+        if let Some(acc) = cache.get_mut(account_id) {
+            if acc.sequence == reserved.sequence + 1 {
+                acc.sequence = reserved.sequence;
+            }
+        }
+    }
+
+    /// Drops any cached state for `account_id`, used after a sequence
+    /// mismatch so the next `next_sequence` call resyncs from chain.
+    pub async fn invalidate(&self, account_id: &AccountId) {
+        self.cache.lock().await.remove(account_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::keyring::{Keyring, COSMOS_BASE_DERIVATION_PATH};
+    use std::cell::Cell;
+    use std::num::NonZeroU32;
+
+    #[test]
+    fn is_sequence_mismatch_matches_only_code_32() {
+        assert!(is_sequence_mismatch(Code::Err(
+            NonZeroU32::new(SEQUENCE_MISMATCH_CODE).unwrap()
+        )));
+        assert!(!is_sequence_mismatch(Code::Err(NonZeroU32::new(1).unwrap())));
+        assert!(!is_sequence_mismatch(Code::Ok));
+    }
+
+    const MNEMONIC: &str =
+        "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    fn test_account_id() -> AccountId {
+        let mut keyring = Keyring::new();
+        keyring
+            .add_key_from_mnemonic("test", MNEMONIC, COSMOS_BASE_DERIVATION_PATH)
+            .unwrap();
+        keyring
+            .sign_key("test")
+            .unwrap()
+            .public_key()
+            .account_id("cosmos")
+            .unwrap()
+    }
+
+    fn seq(sequence: u64) -> AccountSequence {
+        AccountSequence {
+            account_number: 7,
+            sequence,
+        }
+    }
+
+    #[tokio::test]
+    async fn next_sequence_reserves_distinct_values_without_refetching() {
+        let manager = NonceManager::new();
+        let account_id = test_account_id();
+        let fetches = Cell::new(0);
+
+        let first = manager
+            .next_sequence(&account_id, || async {
+                fetches.set(fetches.get() + 1);
+                Ok(seq(5))
+            })
+            .await
+            .unwrap();
+        let second = manager
+            .next_sequence(&account_id, || async { panic!("should use cached entry") })
+            .await
+            .unwrap();
+
+        assert_eq!(first.sequence, 5);
+        assert_eq!(second.sequence, 6);
+        assert_eq!(fetches.get(), 1);
+    }
+
+    #[tokio::test]
+    async fn invalidate_forces_a_refetch() {
+        let manager = NonceManager::new();
+        let account_id = test_account_id();
+
+        manager
+            .next_sequence(&account_id, || async { Ok(seq(5)) })
+            .await
+            .unwrap();
+        manager.invalidate(&account_id).await;
+
+        let refetched = manager
+            .next_sequence(&account_id, || async { Ok(seq(9)) })
+            .await
+            .unwrap();
+
+        assert_eq!(refetched.sequence, 9);
+    }
+
+    #[tokio::test]
+    async fn rollback_restores_an_unused_reservation() {
+        let manager = NonceManager::new();
+        let account_id = test_account_id();
+
+        let reserved = manager
+            .next_sequence(&account_id, || async { Ok(seq(5)) })
+            .await
+            .unwrap();
+        manager.rollback(&account_id, reserved).await;
+
+        let next = manager
+            .next_sequence(&account_id, || async { panic!("should use cached entry") })
+            .await
+            .unwrap();
+
+        assert_eq!(next.sequence, 5);
+    }
+
+    #[tokio::test]
+    async fn rollback_is_a_noop_once_superseded_by_a_later_reservation() {
+        let manager = NonceManager::new();
+        let account_id = test_account_id();
+
+        let first = manager
+            .next_sequence(&account_id, || async { Ok(seq(5)) })
+            .await
+            .unwrap();
+        manager
+            .next_sequence(&account_id, || async { panic!("should use cached entry") })
+            .await
+            .unwrap();
+        manager.rollback(&account_id, first).await;
+
+        let next = manager
+            .next_sequence(&account_id, || async { panic!("should use cached entry") })
+            .await
+            .unwrap();
+
+        assert_eq!(next.sequence, 7);
+    }
+}
@@ -0,0 +1,79 @@
+use cosmrs::tx::Fee;
+use std::time::Duration;
+
+/// Per-tx overrides for values `send_tx` would otherwise compute or
+/// hard-code: the timeout height, memo, fee, and how to wait for the tx to
+/// be included.
+#[derive(Debug, Clone, Default)]
+pub struct TxOptions {
+    pub timeout_height: Option<u16>,
+    pub memo: Option<String>,
+    /// Bypasses gas simulation when set, using this fee as-is.
+    pub fee: Option<Fee>,
+    pub broadcast_mode: BroadcastMode,
+}
+
+/// How `send_tx` should wait for a broadcast tx to be included.
+#[derive(Debug, Clone, Copy)]
+pub enum BroadcastMode {
+    /// Broadcast with `broadcast_tx_commit` and block until the tx is
+    /// committed.
+    Block,
+    /// Broadcast with `broadcast_tx_sync` (returns once `check_tx` passes),
+    /// then poll for the tx to land in a block.
+    Sync(PollOptions),
+    /// Broadcast with `broadcast_tx_async` (returns as soon as the tx is
+    /// accepted into the mempool, without running `check_tx`), then poll
+    /// for the tx to land in a block.
+    Async(PollOptions),
+}
+
+impl Default for BroadcastMode {
+    fn default() -> Self {
+        Self::Block
+    }
+}
+
+/// Polling interval/timeout used by the `Sync`/`Async` broadcast modes so a
+/// slow or throttled endpoint doesn't hang the caller on `broadcast_commit`.
+#[derive(Debug, Clone, Copy)]
+pub struct PollOptions {
+    pub interval: Duration,
+    pub timeout: Duration,
+}
+
+impl Default for PollOptions {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(500),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tx_options_default_leaves_everything_unset_except_broadcast_mode() {
+        let opts = TxOptions::default();
+
+        assert!(opts.timeout_height.is_none());
+        assert!(opts.memo.is_none());
+        assert!(opts.fee.is_none());
+        assert!(matches!(opts.broadcast_mode, BroadcastMode::Block));
+    }
+
+    #[test]
+    fn broadcast_mode_defaults_to_block() {
+        assert!(matches!(BroadcastMode::default(), BroadcastMode::Block));
+    }
+
+    #[test]
+    fn poll_options_default_interval_is_well_under_its_timeout() {
+        let poll = PollOptions::default();
+
+        assert!(poll.interval < poll.timeout);
+    }
+}